@@ -0,0 +1,100 @@
+use graphite::*;
+
+use crate::{Face, MeshData};
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct AABB {
+    pub min: P,
+    pub max: P,
+}
+
+impl AABB {
+    fn of_point(p: P) -> AABB { AABB { min: p, max: p } }
+
+    fn union(&self, other: &AABB) -> AABB {
+        AABB { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    fn grow(&self, p: P) -> AABB { self.union(&AABB::of_point(p)) }
+
+    fn centroid(&self) -> P { (self.min + self.max) * 0.5 }
+
+    fn longest_axis(&self) -> usize {
+        let ext = (self.max.x - self.min.x, self.max.y - self.min.y, self.max.z - self.min.z);
+        if ext.0 >= ext.1 && ext.0 >= ext.2 { 0 }
+        else if ext.1 >= ext.2            { 1 }
+        else                              { 2 }
+    }
+
+    fn along(p: &P, axis: usize) -> f32 {
+        match axis { 0 => p.x, 1 => p.y, _ => p.z }
+    }
+}
+
+pub fn bounds(positions: &[P]) -> Option<AABB> {
+    let (&first, rest) = positions.split_first()?;
+    Some(rest.iter().fold(AABB::of_point(first), |b, &p| b.grow(p)))
+}
+
+pub enum BVH {
+    Leaf { bounds: AABB, faces: Vec<usize> },
+    Node { bounds: AABB, left: Box<BVH>, right: Box<BVH> },
+}
+
+impl BVH {
+    pub fn bounds(&self) -> AABB {
+        match self {
+            BVH::Leaf { bounds, .. } => *bounds,
+            BVH::Node { bounds, .. } => *bounds,
+        }
+    }
+
+    pub fn build(mesh: &MeshData, faces: &[Face]) -> Option<BVH> {
+        if faces.is_empty() {
+            return None;
+        }
+
+        let face_bounds: Vec<AABB> =
+            faces.iter()
+                 .map(|&A3(a, b, c)| {
+                     AABB::of_point(mesh.p[a as usize])
+                          .grow(mesh.p[b as usize])
+                          .grow(mesh.p[c as usize])
+                 })
+                 .collect();
+
+        Some(BVH::build_range(&face_bounds, (0..faces.len()).collect()))
+    }
+
+    fn build_range(face_bounds: &[AABB], mut indices: Vec<usize>) -> BVH {
+        let bounds = indices[1..].iter()
+                                  .fold(face_bounds[indices[0]], |b, &i| b.union(&face_bounds[i]));
+
+        if indices.len() <= LEAF_SIZE {
+            return BVH::Leaf { bounds, faces: indices };
+        }
+
+        let centroid_bounds =
+            indices[1..].iter()
+                        .fold(AABB::of_point(face_bounds[indices[0]].centroid()),
+                              |b, &i| b.grow(face_bounds[i].centroid()));
+        let axis = centroid_bounds.longest_axis();
+
+        indices.sort_by(|&a, &b| {
+            AABB::along(&face_bounds[a].centroid(), axis)
+                .partial_cmp(&AABB::along(&face_bounds[b].centroid(), axis))
+                .unwrap()
+        });
+
+        let right = indices.split_off(indices.len() / 2);
+        let left = indices;
+
+        BVH::Node {
+            bounds,
+            left:  Box::new(BVH::build_range(face_bounds, left)),
+            right: Box::new(BVH::build_range(face_bounds, right)),
+        }
+    }
+}
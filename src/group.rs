@@ -0,0 +1,6 @@
+use std::ops::Range;
+
+pub struct Group {
+    pub name:  String,
+    pub faces: Range<usize>,
+}
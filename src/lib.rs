@@ -1,11 +1,23 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
 
 use anyhow::*;
 
 use graphite::*;
 
+mod bvh;
+mod group;
+mod material;
+mod normals;
+mod writer;
+
+pub use bvh::{bounds, AABB, BVH};
+pub use group::Group;
+pub use material::Material;
+pub use writer::save_to_file;
+
 pub type Face = A3<I>;
 
 #[derive(Default)]
@@ -15,20 +27,42 @@ pub struct MeshData {
     pub uv: Vec<F2>,
 }
 
-pub fn load_from_file(file: &str, to_world: T) -> Result<(MeshData, Vec<Face>)>
+#[derive(Default)]
+pub struct LoadedMesh {
+    pub mesh:      MeshData,
+    pub faces:     Vec<Face>,
+    pub mat_ids:   Vec<I>,
+    pub materials: Vec<Material>,
+    pub groups:    Vec<Group>,
+}
+
+pub fn load_from_file(file: &str, to_world: T, gen_normals: bool) -> Result<LoadedMesh>
 {
     let f = File::open(file)
                  .with_context(|| format!("Error opening OBJ file: {}", file))?;
-    ObjLoader::new(to_world).load(BufReader::new(f))
+    let base_dir = Path::new(file).parent().unwrap_or_else(|| Path::new("."));
+    ObjLoader::new(to_world, base_dir.to_path_buf(), gen_normals).load(BufReader::new(f))
 }
 
 #[derive(Default)]
 struct ObjLoader {
-    tmp_data:   MeshData,
-    obj_data:   MeshData,
-    faces:      Vec<Face>,
-    vertex_map: HashMap<Vertex, I>,
-    to_world:   T,
+    tmp_data:        MeshData,
+    obj_data:        MeshData,
+    faces:           Vec<Face>,
+    mat_ids:         Vec<I>,
+    vertex_map:      HashMap<Vertex, I>,
+    to_world:        T,
+    base_dir:        PathBuf,
+    materials:       Vec<Material>,
+    material_map:    HashMap<String, I>,
+    current_mtl:     I,
+    groups:          Vec<Group>,
+    object_name:     Option<String>,
+    group_name:      Option<String>,
+    group_start:     usize,
+    smoothing_group: Option<I>,
+    off_counter:     I,
+    gen_normals:     bool,
 }
 
 #[derive(Eq, Hash, PartialEq)]
@@ -36,14 +70,22 @@ struct Vertex {
     p: I,
     t: I,
     n: I,
+    s: I,
 }
 
 impl ObjLoader {
-    fn new(to_world: T) -> ObjLoader {
-        ObjLoader { to_world, ..Default::default() }
+    fn new(to_world: T, base_dir: PathBuf, gen_normals: bool) -> ObjLoader {
+        ObjLoader {
+            to_world,
+            base_dir,
+            gen_normals,
+            materials: vec![Material::default()],
+            ..Default::default()
+        }
     }
 
-    fn load(mut self, mut buf: impl BufRead) -> Result<(MeshData, Vec<Face>)> {
+    fn load(mut self, mut buf: impl BufRead) -> Result<LoadedMesh>
+    {
         let mut line = String::with_capacity(120);
         while buf.read_line(&mut line).context("Error reading line")?  > 0 {
             let mut tokens = line[..].split_whitespace();
@@ -53,13 +95,119 @@ impl ObjLoader {
                 Some("vt") => self.add_uv(&mut tokens),
                 Some("vn") => self.add_normal(&mut tokens),
                 Some("f") => self.add_face(&mut tokens),
+                Some("mtllib") => self.load_mtllib(&mut tokens),
+                Some("usemtl") => self.use_material(&mut tokens),
+                Some("o") => self.start_object(&mut tokens),
+                Some("g") => self.start_group(&mut tokens),
+                Some("s") => self.set_smoothing_group(&mut tokens),
                 _ => Ok(()),
             }?;
 
             line.clear();
         }
+        self.flush_group();
+
+        if self.gen_normals && self.obj_data.n.is_empty() {
+            self.obj_data.n = normals::generate(&self.obj_data.p, &self.faces);
+        }
+
+        Ok(LoadedMesh {
+            mesh:      self.obj_data,
+            faces:     self.faces,
+            mat_ids:   self.mat_ids,
+            materials: self.materials,
+            groups:    self.groups,
+        })
+    }
+
+    fn set_smoothing_group<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        self.smoothing_group = match tokens.next() {
+            Some("off") | Some("0") | None => None,
+            Some(tok) => Some(tok.parse().context("invalid smoothing group")?),
+        };
+        Ok(())
+    }
+
+    // `s off`/`s 0` (and no `s` directive at all) all mean "no smoothing", so
+    // each such face must get its own unique key instead of sharing one --
+    // otherwise coincident positions across unrelated flat-shaded faces would
+    // still merge into a single normal accumulator.
+    fn face_smoothing_key(&mut self) -> I {
+        match self.smoothing_group {
+            Some(g) => g,
+            None => {
+                self.off_counter -= 1;
+                self.off_counter
+            }
+        }
+    }
 
-        Ok((self.obj_data, self.faces))
+    fn start_object<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        self.flush_group();
+        self.object_name = tokens.next().map(str::to_string);
+        self.group_name = None;
+        Ok(())
+    }
+
+    fn start_group<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        self.flush_group();
+        self.group_name = tokens.next().map(str::to_string);
+        Ok(())
+    }
+
+    fn current_name(&self) -> String {
+        match (&self.object_name, &self.group_name) {
+            (Some(o), Some(g)) => format!("{}/{}", o, g),
+            (Some(o), None)    => o.clone(),
+            (None, Some(g))    => g.clone(),
+            (None, None)       => "default".to_string(),
+        }
+    }
+
+    fn flush_group(&mut self) {
+        if self.faces.len() > self.group_start {
+            self.groups.push(Group {
+                name:  self.current_name(),
+                faces: self.group_start..self.faces.len(),
+            });
+            self.group_start = self.faces.len();
+        }
+    }
+
+    fn load_mtllib<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        let name = tokens.next().context("mtllib requires a filename")?;
+        match material::load_from_file(&self.base_dir.join(name)) {
+            Ok(materials) => {
+                for material in materials {
+                    self.material_map.insert(material.name.clone(), self.materials.len() as I);
+                    self.materials.push(material);
+                }
+            }
+            Err(e) => eprintln!("warning: skipping unresolvable mtllib {}: {:#}", name, e),
+        }
+        Ok(())
+    }
+
+    fn use_material<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        let name = tokens.next().context("usemtl requires a material name")?;
+        self.current_mtl = match self.material_map.get(name) {
+            Some(&i) => i,
+            None => {
+                eprintln!("warning: unknown material {}, using default", name);
+                0
+            }
+        };
+        Ok(())
     }
 
     fn add_point<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
@@ -77,8 +225,9 @@ impl ObjLoader {
     fn add_face<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
         -> Result<()>
     {
+        let smoothing_key = if self.gen_normals { self.face_smoothing_key() } else { 0 };
         let vertices: Result<Vec<I>, _> =
-            tokens.map(|st| match self.parse_vertex(st) {
+            tokens.map(|st| match self.parse_vertex(st, smoothing_key) {
                       Ok(v) => match self.vertex_map.get(&v) {
                           Some(&i) => Ok(i),
                           None => Ok(self.add_vertex(v)),
@@ -88,13 +237,13 @@ impl ObjLoader {
                   .collect();
         let v = vertices?;
 
-        match v.len() {
-            3 => self.faces.push(A3(v[0], v[1], v[2])),
-            4 => {
-                self.faces.push(A3(v[0], v[1], v[2]));
-                self.faces.push(A3(v[0], v[2], v[3]));
-            }
-            _ => bail!("unexpected number of vertices"),
+        if v.len() < 3 {
+            bail!("unexpected number of vertices");
+        }
+
+        for i in 1..v.len() - 1 {
+            self.faces.push(A3(v[0], v[i], v[i + 1]));
+            self.mat_ids.push(self.current_mtl);
         }
         Ok(())
     }
@@ -112,13 +261,14 @@ impl ObjLoader {
         n
     }
 
-    fn parse_vertex(&mut self, token: &str) -> Result<Vertex> {
+    fn parse_vertex(&mut self, token: &str, smoothing_key: I) -> Result<Vertex> {
         let mut tokens = token.split('/');
         Ok(Vertex {
             p: parse_index(&mut tokens, self.tmp_data.p.len())
                 .context("index for position is required")?,
             t: parse_index(&mut tokens, self.tmp_data.uv.len()).unwrap_or(-1),
             n: parse_index(&mut tokens, self.tmp_data.n.len()).unwrap_or(-1),
+            s: smoothing_key,
         })
     }
 }
@@ -127,13 +277,13 @@ fn parse_index<'a>(tkns: &mut impl Iterator<Item = &'a str>, n: usize)
     -> Result<I>
 { parse(tkns).map(|i: I| if i > 0 { i - 1 } else { i + n as I }) }
 
-fn parse_f3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<F3>
+pub(crate) fn parse_f3<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<F3>
 { Ok(A3(parse(tokens)?, parse(tokens)?, parse(tokens)?)) }
 
 fn parse_f2<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<F2>
 { Ok(A2(parse(tokens)?, parse(tokens)?)) }
 
-fn parse<'a, S>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<S>
+pub(crate) fn parse<'a, S>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<S>
     where S: std::str::FromStr,
           <S as std::str::FromStr>::Err: std::error::Error + Sync + Send
                                        + 'static
@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::*;
+
+use graphite::*;
+
+use crate::{parse, parse_f3};
+
+pub struct Material {
+    pub name:     String,
+    pub ka:       F3,
+    pub kd:       F3,
+    pub ks:       F3,
+    pub ns:       f32,
+    pub d:        f32,
+    pub ni:       f32,
+    pub illum:    i32,
+    pub map_kd:   Option<String>,
+    pub map_ks:   Option<String>,
+    pub map_bump: Option<String>,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material {
+            name:     String::from("default"),
+            ka:       A3(0.0, 0.0, 0.0),
+            kd:       A3(0.8, 0.8, 0.8),
+            ks:       A3(0.0, 0.0, 0.0),
+            ns:       0.0,
+            d:        1.0,
+            ni:       1.0,
+            illum:    2,
+            map_kd:   None,
+            map_ks:   None,
+            map_bump: None,
+        }
+    }
+}
+
+pub fn load_from_file(file: &Path) -> Result<Vec<Material>> {
+    let f = File::open(file)
+                 .with_context(|| format!("Error opening MTL file: {}", file.display()))?;
+    MtlLoader::default().load(BufReader::new(f))
+}
+
+#[derive(Default)]
+struct MtlLoader {
+    materials: Vec<Material>,
+}
+
+impl MtlLoader {
+    fn load(mut self, mut buf: impl BufRead) -> Result<Vec<Material>> {
+        let mut line = String::with_capacity(120);
+        while buf.read_line(&mut line).context("Error reading line")?  > 0 {
+            let mut tokens = line[..].split_whitespace();
+
+            match tokens.next() {
+                Some("newmtl")   => self.add_material(&mut tokens),
+                Some("Ka")       => self.set_ka(&mut tokens),
+                Some("Kd")       => self.set_kd(&mut tokens),
+                Some("Ks")       => self.set_ks(&mut tokens),
+                Some("Ns")       => self.set_ns(&mut tokens),
+                Some("Ni")       => self.set_ni(&mut tokens),
+                Some("d")        => self.set_d(&mut tokens),
+                Some("Tr")       => self.set_tr(&mut tokens),
+                Some("illum")    => self.set_illum(&mut tokens),
+                Some("map_Kd")   => self.set_map_kd(&mut tokens),
+                Some("map_Ks")   => self.set_map_ks(&mut tokens),
+                Some("map_Bump") => self.set_map_bump(&mut tokens),
+                _ => Ok(()),
+            }?;
+
+            line.clear();
+        }
+
+        Ok(self.materials)
+    }
+
+    fn add_material<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>)
+        -> Result<()>
+    {
+        let name = tokens.next().context("newmtl requires a name")?;
+        Ok(self.materials.push(Material { name: name.to_string(), ..Default::default() }))
+    }
+
+    fn current(&mut self) -> Result<&mut Material> {
+        self.materials.last_mut().context("material property specified before newmtl")
+    }
+
+    fn set_ka<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.ka = parse_f3(tokens)?) }
+
+    fn set_kd<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.kd = parse_f3(tokens)?) }
+
+    fn set_ks<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.ks = parse_f3(tokens)?) }
+
+    fn set_ns<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.ns = parse(tokens)?) }
+
+    fn set_ni<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.ni = parse(tokens)?) }
+
+    fn set_d<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.d = parse(tokens)?) }
+
+    fn set_tr<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.d = 1.0 - parse::<f32>(tokens)?) }
+
+    fn set_illum<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    { Ok(self.current()?.illum = parse(tokens)?) }
+
+    fn set_map_kd<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    {
+        let path = tokens.next().context("map_Kd requires a texture path")?;
+        Ok(self.current()?.map_kd = Some(path.to_string()))
+    }
+
+    fn set_map_ks<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    {
+        let path = tokens.next().context("map_Ks requires a texture path")?;
+        Ok(self.current()?.map_ks = Some(path.to_string()))
+    }
+
+    fn set_map_bump<'a>(&mut self, tokens: &mut impl Iterator<Item = &'a str>) -> Result<()>
+    {
+        let path = tokens.next().context("map_Bump requires a texture path")?;
+        Ok(self.current()?.map_bump = Some(path.to_string()))
+    }
+}
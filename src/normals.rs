@@ -0,0 +1,32 @@
+use graphite::*;
+
+use crate::Face;
+
+pub(crate) fn generate(positions: &[P], faces: &[Face]) -> Vec<N> {
+    let mut accum = vec![P::from(A3(0.0, 0.0, 0.0)); positions.len()];
+
+    for &A3(ia, ib, ic) in faces {
+        let (ia, ib, ic) = (ia as usize, ib as usize, ic as usize);
+        let (a, b, c) = (positions[ia], positions[ib], positions[ic]);
+
+        let ab = b - a;
+        let ac = c - a;
+        let face_n = ab.cross(ac);
+
+        if face_n.length() <= 0.0 {
+            continue;
+        }
+
+        accum[ia] = accum[ia] + face_n * corner_angle(ab, ac);
+        accum[ib] = accum[ib] + face_n * corner_angle(a - b, c - b);
+        accum[ic] = accum[ic] + face_n * corner_angle(a - c, b - c);
+    }
+
+    accum.into_iter()
+         .map(|n| if n.length() > 0.0 { N::from(n.normalize()) }
+                  else              { N::from(A3(0.0, 0.0, 1.0)) })
+         .collect()
+}
+
+fn corner_angle(u: P, v: P) -> f32
+{ (u.dot(v) / (u.length() * v.length())).clamp(-1.0, 1.0).acos() }
@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use anyhow::*;
+
+use graphite::*;
+
+use crate::{Face, MeshData};
+
+pub fn save_to_file(file: &str, mesh: &MeshData, faces: &[Face]) -> Result<()> {
+    let f = File::create(file)
+                 .with_context(|| format!("Error creating OBJ file: {}", file))?;
+    write_obj(BufWriter::new(f), mesh, faces)
+}
+
+fn write_obj(mut buf: impl Write, mesh: &MeshData, faces: &[Face]) -> Result<()> {
+    for p in &mesh.p {
+        writeln!(buf, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+
+    for uv in &mesh.uv {
+        writeln!(buf, "vt {} {}", uv.0, uv.1)?;
+    }
+
+    for n in &mesh.n {
+        writeln!(buf, "vn {} {} {}", n.x, n.y, n.z)?;
+    }
+
+    let has_uv = !mesh.uv.is_empty();
+    let has_n  = !mesh.n.is_empty();
+
+    for &A3(a, b, c) in faces {
+        writeln!(buf, "f {} {} {}",
+                 vertex_token(a, has_uv, has_n),
+                 vertex_token(b, has_uv, has_n),
+                 vertex_token(c, has_uv, has_n))?;
+    }
+
+    Ok(())
+}
+
+fn vertex_token(i: I, has_uv: bool, has_n: bool) -> String {
+    let i = i + 1;
+    match (has_uv, has_n) {
+        (true, true)   => format!("{0}/{0}/{0}", i),
+        (true, false)  => format!("{0}/{0}", i),
+        (false, true)  => format!("{0}//{0}", i),
+        (false, false) => format!("{0}", i),
+    }
+}